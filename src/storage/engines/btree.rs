@@ -1,4 +1,5 @@
-use super::KvEngine;
+use super::super::wal::{WalEntry, WriteBatch};
+use super::super::KvEngine;
 use std::collections::HashMap;
 
 pub struct BTreeStore {
@@ -14,13 +15,45 @@ impl BTreeStore {
         self.map.insert(key.to_vec(), value.to_vec());
     }
 
-    pub fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
-        self.map.get(key)
+    pub fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.map.get(key).cloned()
     }
 
     pub fn delete(&mut self, key: &[u8]) {
         self.map.remove(key);
     }
+
+    /// All entries with `start <= key < end`, in ascending key order.
+    /// There's no sorted index here, so this sorts on every call.
+    pub fn scan(&self, start: &[u8], end: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut entries: Vec<(Vec<u8>, Vec<u8>)> = self
+            .map
+            .iter()
+            .filter(|(k, _)| k.as_slice() >= start && k.as_slice() < end)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Apply every op in `batch` under the same map. There's no WAL here
+    /// to frame the batch as one record, but since this is a plain
+    /// in-memory map the ops simply all land (or, pre-crash, don't exist
+    /// at all) — there's no intermediate state to observe.
+    pub fn write(&mut self, batch: WriteBatch) {
+        for op in batch.ops() {
+            match op {
+                WalEntry::Put { key, value } => self.put(key, value),
+                WalEntry::Delete { key } => self.delete(key),
+            }
+        }
+    }
+}
+
+impl Default for BTreeStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl KvEngine for BTreeStore {
@@ -32,11 +65,19 @@ impl KvEngine for BTreeStore {
         self.put(key, value);
     }
 
-    fn get(&self, key: &[u8]) -> Option<&Vec<u8>> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
         self.get(key)
     }
 
     fn delete(&mut self, key: &[u8]) {
         self.delete(key);
     }
+
+    fn write(&mut self, batch: WriteBatch) {
+        self.write(batch);
+    }
+
+    fn scan<'a>(&'a self, start: &[u8], end: &[u8]) -> Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a> {
+        Box::new(self.scan(start, end).into_iter())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,136 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits allocated per key so the filter lands at roughly a 1% false
+/// positive rate (the standard `m/n ~= 9.6` ratio for Bloom filters).
+const BITS_PER_KEY: f64 = 9.6;
+
+/// A Bloom filter over a single SSTable's keys, used to skip a block read
+/// entirely when a key is definitely not present.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /// Build an empty filter sized for `num_keys` entries at ~1% FPR.
+    pub fn with_capacity(num_keys: usize) -> Self {
+        let n = num_keys.max(1) as f64;
+        let num_bits = (n * BITS_PER_KEY).ceil().max(8.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * std::f64::consts::LN_2).round().max(1.0) as u32;
+        let byte_len = num_bits.div_ceil(8) as usize;
+        Self {
+            bits: vec![0u8; byte_len],
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /// Derive the two seed hashes used for double hashing: `h_i = h1 + i*h2`.
+    fn seed_hashes(key: &[u8]) -> (u64, u64) {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let h = hasher.finish();
+        let h1 = h & 0xFFFF_FFFF;
+        let h2 = h >> 32;
+        // A zero step would collapse every probe onto h1.
+        (h1, if h2 == 0 { 1 } else { h2 })
+    }
+
+    fn bit_positions(num_bits: u64, num_hashes: u32, key: &[u8]) -> Vec<u64> {
+        let (h1, h2) = Self::seed_hashes(key);
+        (0..num_hashes as u64)
+            .map(|i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+            .collect()
+    }
+
+    pub fn insert(&mut self, key: &[u8]) {
+        for pos in Self::bit_positions(self.num_bits, self.num_hashes, key) {
+            let byte = (pos / 8) as usize;
+            let bit = (pos % 8) as u8;
+            self.bits[byte] |= 1 << bit;
+        }
+    }
+
+    /// True if the key *might* be present; false means it's definitely absent.
+    pub fn may_contain(&self, key: &[u8]) -> bool {
+        Self::bit_positions(self.num_bits, self.num_hashes, key)
+            .into_iter()
+            .all(|pos| {
+                let byte = (pos / 8) as usize;
+                let bit = (pos % 8) as u8;
+                self.bits[byte] & (1 << bit) != 0
+            })
+    }
+
+    /// Serialize as `[num_bits: u64][num_hashes: u32][bits...]`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + 4 + self.bits.len());
+        buf.extend_from_slice(&self.num_bits.to_le_bytes());
+        buf.extend_from_slice(&self.num_hashes.to_le_bytes());
+        buf.extend_from_slice(&self.bits);
+        buf
+    }
+
+    /// Deserialize a filter previously written by `encode`.
+    pub fn decode(buf: &[u8]) -> Self {
+        let num_bits = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+        let num_hashes = u32::from_le_bytes(buf[8..12].try_into().unwrap());
+        let bits = buf[12..].to_vec();
+        Self {
+            bits,
+            num_bits,
+            num_hashes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inserted_keys_always_may_contain() {
+        let keys: Vec<Vec<u8>> = (0..500).map(|i| format!("key{i}").into_bytes()).collect();
+        let mut filter = BloomFilter::with_capacity(keys.len());
+        for key in &keys {
+            filter.insert(key);
+        }
+        for key in &keys {
+            assert!(filter.may_contain(key), "false negative for {key:?}");
+        }
+    }
+
+    #[test]
+    fn test_absent_keys_are_mostly_rejected() {
+        let present: Vec<Vec<u8>> = (0..500).map(|i| format!("present{i}").into_bytes()).collect();
+        let mut filter = BloomFilter::with_capacity(present.len());
+        for key in &present {
+            filter.insert(key);
+        }
+
+        let false_positives = (0..500)
+            .map(|i| format!("absent{i}").into_bytes())
+            .filter(|key| filter.may_contain(key))
+            .count();
+        // Sized for ~1% FPR; fewer than 10% of the sample should trip it.
+        assert!(false_positives < 50, "too many false positives: {false_positives}/500");
+    }
+
+    #[test]
+    fn test_encode_decode_roundtrip_preserves_membership() {
+        let keys: Vec<Vec<u8>> = (0..100).map(|i| format!("k{i}").into_bytes()).collect();
+        let mut filter = BloomFilter::with_capacity(keys.len());
+        for key in &keys {
+            filter.insert(key);
+        }
+
+        let decoded = BloomFilter::decode(&filter.encode());
+        for key in &keys {
+            assert!(decoded.may_contain(key));
+        }
+        assert!(!decoded.may_contain(b"definitely-not-inserted-xyz"));
+    }
+}
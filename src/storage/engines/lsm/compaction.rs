@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use super::internal_key::InternalKey;
+use super::sstable::{SSTable, StoredValue};
+
+/// L0 tables may have overlapping key ranges; once there are more than
+/// this many, a compaction merges them (plus any overlapping L1 tables) down into L1.
+pub const L0_COMPACTION_TRIGGER: usize = 4;
+
+/// Byte budget for L1. Level `Ln`'s budget is `L1_MAX_BYTES * 10^(n-1)`.
+pub const L1_MAX_BYTES: u64 = 4 * 1024 * 1024;
+
+const LEVEL_SIZE_MULTIPLIER: u64 = 10;
+
+/// The byte budget for level `level` (must be >= 1).
+pub fn level_budget_bytes(level: u8) -> u64 {
+    debug_assert!(level >= 1, "L0 has no byte budget, only a file-count trigger");
+    L1_MAX_BYTES.saturating_mul(LEVEL_SIZE_MULTIPLIER.pow((level - 1) as u32))
+}
+
+/// Whether two inclusive key ranges overlap.
+pub fn ranges_overlap(a_min: &[u8], a_max: &[u8], b_min: &[u8], b_max: &[u8]) -> bool {
+    a_min <= b_max && b_min <= a_max
+}
+
+/// Merge-sort every version of every key across `tables`, dropping
+/// whichever versions no read — live or future — could ever select.
+///
+/// For each key, the newest version is always kept: an unsnapshotted
+/// read, or a snapshot taken after it, must still be able to find it. An
+/// older version is kept only if some entry in `live_snapshot_seqs` falls
+/// in the range it alone covers — at or after its own seq, before the
+/// next newer version's seq — since that's the only way any read, past
+/// or future, could ever select it. If the newest version is a
+/// tombstone and `is_bottom` is set (this merge's output is the deepest
+/// level that could hold this key), it's dropped outright unless an older
+/// version is being kept for a live snapshot: with no older version left
+/// beneath it to resurrect, an absent key reads back as "deleted" either
+/// way, live snapshots included.
+pub fn merge_tables(
+    tables: &[&SSTable],
+    is_bottom: bool,
+    live_snapshot_seqs: &[u64],
+) -> std::io::Result<Vec<(InternalKey, StoredValue)>> {
+    let mut by_key: HashMap<Vec<u8>, Vec<(u64, StoredValue)>> = HashMap::new();
+    for table in tables {
+        for (ikey, value) in table.read_all()? {
+            by_key.entry(ikey.user_key).or_default().push((ikey.seq, value));
+        }
+    }
+
+    let mut entries: Vec<(InternalKey, StoredValue)> = Vec::new();
+    for (user_key, mut versions) in by_key {
+        // Newest (highest seq) first.
+        versions.sort_by_key(|&(seq, _)| std::cmp::Reverse(seq));
+
+        let mut older_kept: Vec<(InternalKey, StoredValue)> = Vec::new();
+        for pair in versions.windows(2) {
+            let newer_seq = pair[0].0;
+            let (seq, value) = &pair[1];
+            let still_needed = live_snapshot_seqs.iter().any(|&s| *seq <= s && s < newer_seq);
+            if still_needed {
+                older_kept.push((InternalKey::new(user_key.clone(), *seq), value.clone()));
+            }
+        }
+
+        // A tombstone still shadowing a kept older version must stay, or
+        // that version would read back as current.
+        let (newest_seq, newest_value) = &versions[0];
+        let drop_newest =
+            is_bottom && matches!(newest_value, StoredValue::Tombstone) && older_kept.is_empty();
+        if !drop_newest {
+            entries.push((InternalKey::new(user_key.clone(), *newest_seq), newest_value.clone()));
+        }
+        entries.extend(older_kept);
+    }
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_budget_bytes_grows_by_multiplier_per_level() {
+        assert_eq!(level_budget_bytes(1), L1_MAX_BYTES);
+        assert_eq!(level_budget_bytes(2), L1_MAX_BYTES * LEVEL_SIZE_MULTIPLIER);
+        assert_eq!(level_budget_bytes(3), L1_MAX_BYTES * LEVEL_SIZE_MULTIPLIER * LEVEL_SIZE_MULTIPLIER);
+    }
+
+    #[test]
+    fn test_ranges_overlap() {
+        assert!(ranges_overlap(b"a", b"m", b"g", b"z"));
+        assert!(ranges_overlap(b"a", b"m", b"m", b"z"));
+        assert!(!ranges_overlap(b"a", b"m", b"n", b"z"));
+        assert!(!ranges_overlap(b"n", b"z", b"a", b"m"));
+    }
+
+    fn scratch_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("toydb_compaction_test_{name}.sst"))
+    }
+
+    fn write_sorted(name: &str, mut entries: Vec<(InternalKey, StoredValue)>) -> SSTable {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let path = scratch_path(name);
+        SSTable::write(&path, 0, 0, &entries).expect("write failed")
+    }
+
+    #[test]
+    fn test_merge_tables_keeps_only_newest_version_with_no_live_snapshots() {
+        let table = write_sorted(
+            "newest_only",
+            vec![
+                (InternalKey::new(b"k".to_vec(), 1), StoredValue::Value(b"v1".to_vec())),
+                (InternalKey::new(b"k".to_vec(), 2), StoredValue::Value(b"v2".to_vec())),
+            ],
+        );
+
+        let merged = merge_tables(&[&table], false, &[]).expect("merge failed");
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].0.seq, 2);
+        assert!(matches!(&merged[0].1, StoredValue::Value(v) if v == b"v2"));
+
+        std::fs::remove_file(&table.path).ok();
+    }
+
+    #[test]
+    fn test_merge_tables_drops_bottom_level_tombstone_but_keeps_it_elsewhere() {
+        let table = write_sorted(
+            "tombstone",
+            vec![
+                (InternalKey::new(b"k".to_vec(), 1), StoredValue::Value(b"v1".to_vec())),
+                (InternalKey::new(b"k".to_vec(), 2), StoredValue::Tombstone),
+            ],
+        );
+
+        let at_bottom = merge_tables(&[&table], true, &[]).expect("merge failed");
+        assert!(at_bottom.is_empty(), "bottom-level tombstone with no live snapshot should be dropped");
+
+        let not_bottom = merge_tables(&[&table], false, &[]).expect("merge failed");
+        assert_eq!(not_bottom.len(), 1);
+        assert!(matches!(&not_bottom[0].1, StoredValue::Tombstone));
+
+        std::fs::remove_file(&table.path).ok();
+    }
+
+    #[test]
+    fn test_merge_tables_keeps_older_version_a_live_snapshot_still_needs() {
+        let table = write_sorted(
+            "snapshot",
+            vec![
+                (InternalKey::new(b"k".to_vec(), 1), StoredValue::Value(b"v1".to_vec())),
+                (InternalKey::new(b"k".to_vec(), 5), StoredValue::Value(b"v5".to_vec())),
+            ],
+        );
+
+        // A snapshot at seq 3 can only ever see the version written at
+        // seq 1 (the next version isn't visible to it until seq 5), so
+        // that version must survive the merge even though seq 5 shadows it.
+        let merged = merge_tables(&[&table], true, &[3]).expect("merge failed");
+        let mut seqs: Vec<u64> = merged.iter().map(|(k, _)| k.seq).collect();
+        seqs.sort_unstable();
+        assert_eq!(seqs, vec![1, 5]);
+
+        std::fs::remove_file(&table.path).ok();
+    }
+}
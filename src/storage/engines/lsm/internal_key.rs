@@ -0,0 +1,59 @@
+use std::cmp::Ordering;
+
+/// A versioned key: a user key paired with the sequence number of the
+/// write that produced it. Ordered by user key ascending, then by
+/// sequence *descending* — so wherever a key's versions are stored
+/// (MemTable, SSTable), the newest version always sorts first and a
+/// point-in-time read can just take the first version at or below its
+/// snapshot sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalKey {
+    pub user_key: Vec<u8>,
+    pub seq: u64,
+}
+
+impl InternalKey {
+    pub fn new(user_key: Vec<u8>, seq: u64) -> Self {
+        Self { user_key, seq }
+    }
+}
+
+impl Ord for InternalKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.user_key
+            .cmp(&other.user_key)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for InternalKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orders_by_user_key_ascending() {
+        let a = InternalKey::new(b"a".to_vec(), 1);
+        let b = InternalKey::new(b"b".to_vec(), 1);
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_orders_same_user_key_by_seq_descending() {
+        let newer = InternalKey::new(b"k".to_vec(), 5);
+        let older = InternalKey::new(b"k".to_vec(), 1);
+        assert!(newer < older, "higher seq of the same key should sort first");
+    }
+
+    #[test]
+    fn test_shorter_key_sorts_before_its_own_prefix_extension() {
+        let short = InternalKey::new(b"a".to_vec(), 1);
+        let long = InternalKey::new(b"ab".to_vec(), 1);
+        assert!(short < long);
+    }
+}
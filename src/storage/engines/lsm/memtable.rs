@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use super::internal_key::InternalKey;
+use super::sstable::StoredValue;
+
+/// In-memory table of the most recent writes, flushed to an SSTable once
+/// it grows past `LsmStore`'s size threshold. Every write keeps its own
+/// version under its sequence number (rather than overwriting the prior
+/// one in place), so a point-in-time snapshot can still read a key as it
+/// stood before a later write. Keyed by `InternalKey` so entries stay
+/// sorted by user key ascending, then by sequence descending — both a
+/// flush and a range scan can walk it in that order directly.
+pub struct MemTable {
+    entries: BTreeMap<InternalKey, StoredValue>,
+    size_bytes: usize,
+}
+
+impl MemTable {
+    pub fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            size_bytes: 0,
+        }
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8], seq: u64) {
+        self.size_bytes += key.len() + value.len();
+        self.entries
+            .insert(InternalKey::new(key.to_vec(), seq), StoredValue::Value(value.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: &[u8], seq: u64) {
+        self.size_bytes += key.len();
+        self.entries
+            .insert(InternalKey::new(key.to_vec(), seq), StoredValue::Tombstone);
+    }
+
+    /// The newest version of `key` with a sequence `<= max_seq`, if any.
+    pub fn get_at(&self, key: &[u8], max_seq: u64) -> Option<&StoredValue> {
+        let start = InternalKey::new(key.to_vec(), u64::MAX);
+        self.entries
+            .range(start..)
+            .take_while(|(ikey, _)| ikey.user_key == key)
+            .find(|(ikey, _)| ikey.seq <= max_seq)
+            .map(|(_, value)| value)
+    }
+
+    /// The newest version of each key with `start <= key < end` and a
+    /// sequence `<= max_seq`, in ascending key order. Keys with no
+    /// qualifying version (every write to them happened after `max_seq`)
+    /// are omitted entirely.
+    pub fn range_at(&self, start: &[u8], end: &[u8], max_seq: u64) -> Vec<(Vec<u8>, StoredValue)> {
+        let lower = InternalKey::new(start.to_vec(), u64::MAX);
+        let upper = InternalKey::new(end.to_vec(), u64::MAX);
+
+        let mut out: Vec<(Vec<u8>, StoredValue)> = Vec::new();
+        for (ikey, value) in self.entries.range(lower..upper) {
+            if ikey.seq > max_seq {
+                continue;
+            }
+            match out.last() {
+                Some((last_key, _)) if *last_key == ikey.user_key => {}
+                _ => out.push((ikey.user_key.clone(), value.clone())),
+            }
+        }
+        out
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// A snapshot of every version of every key, in the order an SSTable
+    /// needs them: ascending by user key, newest version first.
+    pub fn sorted_entries(&self) -> Vec<(InternalKey, StoredValue)> {
+        self.entries
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size_bytes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_at_returns_newest_version_not_after_max_seq() {
+        let mut table = MemTable::new();
+        table.put(b"k", b"v1", 1);
+        table.put(b"k", b"v2", 2);
+
+        assert!(matches!(table.get_at(b"k", 1), Some(StoredValue::Value(v)) if v == b"v1"));
+        assert!(matches!(table.get_at(b"k", 2), Some(StoredValue::Value(v)) if v == b"v2"));
+        assert!(table.get_at(b"k", 0).is_none());
+    }
+
+    #[test]
+    fn test_get_at_returns_tombstone_once_delete_is_visible() {
+        let mut table = MemTable::new();
+        table.put(b"k", b"v1", 1);
+        table.delete(b"k", 2);
+
+        assert!(matches!(table.get_at(b"k", 2), Some(StoredValue::Tombstone)));
+        assert!(matches!(table.get_at(b"k", 1), Some(StoredValue::Value(v)) if v == b"v1"));
+    }
+
+    #[test]
+    fn test_range_at_yields_one_qualifying_version_per_key_in_order() {
+        let mut table = MemTable::new();
+        table.put(b"a", b"a1", 1);
+        table.put(b"b", b"b1", 1);
+        table.put(b"b", b"b2", 2);
+        table.put(b"c", b"c1", 5);
+
+        // Key "c" was only written after max_seq, so it's omitted entirely.
+        let out = table.range_at(b"a", b"d", 2);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].0, b"a");
+        assert_eq!(out[1].0, b"b");
+        assert!(matches!(&out[1].1, StoredValue::Value(v) if v == b"b2"));
+    }
+
+    #[test]
+    fn test_clear_resets_entries_and_size() {
+        let mut table = MemTable::new();
+        table.put(b"k", b"v", 1);
+        assert!(!table.is_empty());
+
+        table.clear();
+        assert!(table.is_empty());
+        assert_eq!(table.size_bytes(), 0);
+        assert!(table.get_at(b"k", u64::MAX).is_none());
+    }
+}
@@ -0,0 +1,146 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::vec::IntoIter;
+
+use super::sstable::StoredValue;
+
+/// One source's next not-yet-yielded entry, ordered so a `BinaryHeap`
+/// (a max-heap) pops the smallest key first, with ties broken in favor
+/// of the lowest `source` index — by convention the newest source.
+struct HeapEntry {
+    key: Vec<u8>,
+    source: usize,
+    value: StoredValue,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .cmp(&self.key)
+            .then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.source == other.source
+    }
+}
+
+impl Eq for HeapEntry {}
+
+/// A lazy k-way merge over several already-sorted, already-range-filtered
+/// sequences of entries (e.g. the MemTable plus one scan per SSTable).
+/// `sources[0]` must be the newest source and each later source
+/// progressively older — ties on a key are resolved in favor of the
+/// lowest index, and tombstones (and every older entry they shadow) are
+/// dropped from the output.
+pub struct MergeIter {
+    heap: BinaryHeap<HeapEntry>,
+    sources: Vec<IntoIter<(Vec<u8>, StoredValue)>>,
+}
+
+impl MergeIter {
+    pub fn new(sources: Vec<Vec<(Vec<u8>, StoredValue)>>) -> Self {
+        let mut sources: Vec<IntoIter<(Vec<u8>, StoredValue)>> =
+            sources.into_iter().map(|s| s.into_iter()).collect();
+
+        let mut heap = BinaryHeap::new();
+        for (source, iter) in sources.iter_mut().enumerate() {
+            if let Some((key, value)) = iter.next() {
+                heap.push(HeapEntry { key, source, value });
+            }
+        }
+
+        Self { heap, sources }
+    }
+
+    /// Pull the next entry from `source` into the heap, if it has one.
+    fn refill(&mut self, source: usize) {
+        if let Some((key, value)) = self.sources[source].next() {
+            self.heap.push(HeapEntry { key, source, value });
+        }
+    }
+}
+
+impl Iterator for MergeIter {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let HeapEntry { key, source, value } = self.heap.pop()?;
+            self.refill(source);
+
+            // Every other source's entry for this key is now shadowed —
+            // drain them so they're never yielded.
+            while let Some(top) = self.heap.peek() {
+                if top.key != key {
+                    break;
+                }
+                let shadowed = self.heap.pop().unwrap();
+                self.refill(shadowed.source);
+            }
+
+            match value {
+                StoredValue::Value(v) => return Some((key, v)),
+                StoredValue::Tombstone => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn val(s: &str) -> StoredValue {
+        StoredValue::Value(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_merges_disjoint_sources_in_key_order() {
+        let a = vec![(b"a".to_vec(), val("a1")), (b"c".to_vec(), val("c1"))];
+        let b = vec![(b"b".to_vec(), val("b1"))];
+
+        let merged: Vec<(Vec<u8>, Vec<u8>)> = MergeIter::new(vec![a, b]).collect();
+        assert_eq!(
+            merged,
+            vec![
+                (b"a".to_vec(), b"a1".to_vec()),
+                (b"b".to_vec(), b"b1".to_vec()),
+                (b"c".to_vec(), b"c1".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lower_index_source_shadows_higher_index_source_on_tie() {
+        let newest = vec![(b"k".to_vec(), val("new"))];
+        let older = vec![(b"k".to_vec(), val("old"))];
+
+        let merged: Vec<(Vec<u8>, Vec<u8>)> = MergeIter::new(vec![newest, older]).collect();
+        assert_eq!(merged, vec![(b"k".to_vec(), b"new".to_vec())]);
+    }
+
+    #[test]
+    fn test_tombstone_suppresses_every_older_source_for_that_key() {
+        let newest = vec![(b"k".to_vec(), StoredValue::Tombstone)];
+        let older = vec![(b"k".to_vec(), val("old"))];
+
+        let merged: Vec<(Vec<u8>, Vec<u8>)> = MergeIter::new(vec![newest, older]).collect();
+        assert!(merged.is_empty());
+    }
+
+    #[test]
+    fn test_empty_sources_yield_nothing() {
+        let merged: Vec<(Vec<u8>, Vec<u8>)> = MergeIter::new(vec![vec![], vec![]]).collect();
+        assert!(merged.is_empty());
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    fs::File,
+    io::{Read, Result, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use super::bloom::BloomFilter;
+use super::internal_key::InternalKey;
+
+/// Target size of a data block before a new one is started.
+const DATA_BLOCK_SIZE: usize = 4 * 1024;
+
+/// Marks the end of the file:
+/// `[index_offset: 8][index_len: 8][bloom_offset: 8][bloom_len: 8][meta_offset: 8][meta_len: 8][magic: 8]`
+const FOOTER_SIZE: u64 = 8 + 8 + 8 + 8 + 8 + 8 + 8;
+const MAGIC: u64 = 0x53535442_4C4B3031; // arbitrary "sstable block" marker
+
+const VALUE_FLAG: u8 = 0;
+const TOMBSTONE_FLAG: u8 = 1;
+
+/// A value as stored in an SSTable: either a live value or a delete marker.
+#[derive(Debug, Clone)]
+pub enum StoredValue {
+    Value(Vec<u8>),
+    Tombstone,
+}
+
+/// One entry in an SSTable's sparse block index: the first internal key
+/// of a data block and where that block lives in the file.
+struct IndexEntry {
+    first_key: InternalKey,
+    offset: u64,
+    len: u32,
+}
+
+/// An immutable, sorted on-disk table produced by flushing a MemTable (or
+/// by compaction). Data is laid out as:
+///
+/// `[data block 0][data block 1]...[index][bloom filter][metadata][footer]`
+///
+/// Each data block holds length-prefixed `[key][seq][tombstone flag][value]`
+/// records, one per key *version* — a table can hold several versions of
+/// the same user key, newest first, so a point-in-time read can still
+/// find one that predates a later write. The index maps each block's
+/// first internal key to its file offset so a lookup only ever reads the
+/// one-or-few blocks that could contain a key, and the Bloom filter
+/// (keyed on the user key) lets it skip the read entirely on a definite
+/// miss. The metadata section holds everything else needed to rebuild an
+/// `SSTable` on `open()` without re-scanning the data blocks: level,
+/// table id, highest sequence number stored, and min/max user key.
+pub struct SSTable {
+    pub path: PathBuf,
+    pub level: u8,
+    /// Smallest/largest *user* key stored in this table (ignoring
+    /// version), used to cheaply rule a table out of a lookup or scan.
+    pub min_key: Vec<u8>,
+    pub max_key: Vec<u8>,
+    pub size: u64,
+    /// Monotonically increasing creation order, used by compaction to
+    /// pick which table to merge next — unrelated to the per-write
+    /// sequence numbers stored alongside each key.
+    pub table_id: u64,
+    /// Highest sequence number of any version stored in this table, used
+    /// to restore `LsmStore`'s sequence counter across a restart.
+    pub max_seq: u64,
+    /// Length of the data-block region (bytes 0..data_len), so a full
+    /// table scan (used by compaction) doesn't need to walk the index.
+    data_len: u64,
+    index: Vec<IndexEntry>,
+    bloom: BloomFilter,
+}
+
+impl SSTable {
+    /// Write `entries` (already sorted ascending by internal key) out as
+    /// a new SSTable file at `path`. `table_id` is the table's creation
+    /// order, used by compaction to pick the oldest table at a level.
+    pub fn write<P: AsRef<Path>>(
+        path: P,
+        level: u8,
+        table_id: u64,
+        entries: &[(InternalKey, StoredValue)],
+    ) -> Result<SSTable> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::create(&path)?;
+        let mut bloom = BloomFilter::with_capacity(entries.len());
+
+        let mut index = Vec::new();
+        let mut offset: u64 = 0;
+        let mut block_buf = Vec::with_capacity(DATA_BLOCK_SIZE);
+        let mut block_first_key: Option<InternalKey> = None;
+
+        for (key, value) in entries {
+            bloom.insert(&key.user_key);
+            let encoded = Self::encode_entry(key, value);
+
+            if !block_buf.is_empty() && block_buf.len() + encoded.len() > DATA_BLOCK_SIZE {
+                Self::flush_block(&mut file, &mut index, &mut offset, &block_buf, &block_first_key)?;
+                block_buf.clear();
+                block_first_key = None;
+            }
+            if block_first_key.is_none() {
+                block_first_key = Some(key.clone());
+            }
+            block_buf.extend_from_slice(&encoded);
+        }
+        if !block_buf.is_empty() {
+            Self::flush_block(&mut file, &mut index, &mut offset, &block_buf, &block_first_key)?;
+        }
+
+        let index_offset = offset;
+        let mut index_buf = Vec::new();
+        for entry in &index {
+            index_buf.extend_from_slice(&(entry.first_key.user_key.len() as u32).to_le_bytes());
+            index_buf.extend_from_slice(&entry.first_key.user_key);
+            index_buf.extend_from_slice(&entry.first_key.seq.to_le_bytes());
+            index_buf.extend_from_slice(&entry.offset.to_le_bytes());
+            index_buf.extend_from_slice(&entry.len.to_le_bytes());
+        }
+        file.write_all(&index_buf)?;
+        offset += index_buf.len() as u64;
+
+        let bloom_offset = offset;
+        let bloom_buf = bloom.encode();
+        file.write_all(&bloom_buf)?;
+        offset += bloom_buf.len() as u64;
+
+        // entries is sorted ascending by internal key (user key, then seq
+        // descending), so the first/last entries give the user-key bounds;
+        // the max seq can belong to any key, so it takes a full pass.
+        let min_key = entries.first().map(|(k, _)| k.user_key.clone()).unwrap_or_default();
+        let max_key = entries.last().map(|(k, _)| k.user_key.clone()).unwrap_or_default();
+        let max_seq = entries.iter().map(|(k, _)| k.seq).max().unwrap_or(0);
+
+        let meta_offset = offset;
+        let mut meta_buf = Vec::new();
+        meta_buf.push(level);
+        meta_buf.extend_from_slice(&table_id.to_le_bytes());
+        meta_buf.extend_from_slice(&max_seq.to_le_bytes());
+        meta_buf.extend_from_slice(&(min_key.len() as u32).to_le_bytes());
+        meta_buf.extend_from_slice(&min_key);
+        meta_buf.extend_from_slice(&(max_key.len() as u32).to_le_bytes());
+        meta_buf.extend_from_slice(&max_key);
+        file.write_all(&meta_buf)?;
+        offset += meta_buf.len() as u64;
+
+        file.write_all(&index_offset.to_le_bytes())?;
+        file.write_all(&(index_buf.len() as u64).to_le_bytes())?;
+        file.write_all(&bloom_offset.to_le_bytes())?;
+        file.write_all(&(bloom_buf.len() as u64).to_le_bytes())?;
+        file.write_all(&meta_offset.to_le_bytes())?;
+        file.write_all(&(meta_buf.len() as u64).to_le_bytes())?;
+        file.write_all(&MAGIC.to_le_bytes())?;
+        file.flush()?;
+
+        let size = offset + FOOTER_SIZE;
+
+        Ok(SSTable {
+            path,
+            level,
+            min_key,
+            max_key,
+            size,
+            table_id,
+            max_seq,
+            data_len: index_offset,
+            index,
+            bloom,
+        })
+    }
+
+    /// Reopen a table previously written by `write`, reading back its
+    /// footer, metadata, sparse index, and Bloom filter so it's ready for
+    /// `get_at`/`scan_at` without re-scanning any data blocks. Called on
+    /// startup to restore every `.sst` file left behind by a prior process.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SSTable> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let size = file.metadata()?.len();
+
+        file.seek(SeekFrom::Start(size - FOOTER_SIZE))?;
+        let mut footer = [0u8; FOOTER_SIZE as usize];
+        file.read_exact(&mut footer)?;
+        let index_offset = u64::from_le_bytes(footer[0..8].try_into().unwrap());
+        let index_len = u64::from_le_bytes(footer[8..16].try_into().unwrap());
+        let bloom_offset = u64::from_le_bytes(footer[16..24].try_into().unwrap());
+        let bloom_len = u64::from_le_bytes(footer[24..32].try_into().unwrap());
+        let meta_offset = u64::from_le_bytes(footer[32..40].try_into().unwrap());
+        let meta_len = u64::from_le_bytes(footer[40..48].try_into().unwrap());
+        let magic = u64::from_le_bytes(footer[48..56].try_into().unwrap());
+        if magic != MAGIC {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: bad SSTable footer magic", path.display()),
+            ));
+        }
+
+        file.seek(SeekFrom::Start(meta_offset))?;
+        let mut meta_buf = vec![0u8; meta_len as usize];
+        file.read_exact(&mut meta_buf)?;
+        let mut off = 0usize;
+        let level = meta_buf[off];
+        off += 1;
+        let table_id = u64::from_le_bytes(meta_buf[off..off + 8].try_into().unwrap());
+        off += 8;
+        let max_seq = u64::from_le_bytes(meta_buf[off..off + 8].try_into().unwrap());
+        off += 8;
+        let min_key_len = u32::from_le_bytes(meta_buf[off..off + 4].try_into().unwrap()) as usize;
+        off += 4;
+        let min_key = meta_buf[off..off + min_key_len].to_vec();
+        off += min_key_len;
+        let max_key_len = u32::from_le_bytes(meta_buf[off..off + 4].try_into().unwrap()) as usize;
+        off += 4;
+        let max_key = meta_buf[off..off + max_key_len].to_vec();
+
+        file.seek(SeekFrom::Start(index_offset))?;
+        let mut index_buf = vec![0u8; index_len as usize];
+        file.read_exact(&mut index_buf)?;
+        let mut index = Vec::new();
+        let mut off = 0usize;
+        while off < index_buf.len() {
+            let key_len = u32::from_le_bytes(index_buf[off..off + 4].try_into().unwrap()) as usize;
+            off += 4;
+            let user_key = index_buf[off..off + key_len].to_vec();
+            off += key_len;
+            let seq = u64::from_le_bytes(index_buf[off..off + 8].try_into().unwrap());
+            off += 8;
+            let block_offset = u64::from_le_bytes(index_buf[off..off + 8].try_into().unwrap());
+            off += 8;
+            let block_len = u32::from_le_bytes(index_buf[off..off + 4].try_into().unwrap());
+            off += 4;
+            index.push(IndexEntry {
+                first_key: InternalKey::new(user_key, seq),
+                offset: block_offset,
+                len: block_len,
+            });
+        }
+
+        file.seek(SeekFrom::Start(bloom_offset))?;
+        let mut bloom_buf = vec![0u8; bloom_len as usize];
+        file.read_exact(&mut bloom_buf)?;
+        let bloom = BloomFilter::decode(&bloom_buf);
+
+        Ok(SSTable {
+            path,
+            level,
+            min_key,
+            max_key,
+            size,
+            table_id,
+            max_seq,
+            data_len: index_offset,
+            index,
+            bloom,
+        })
+    }
+
+    fn flush_block(
+        file: &mut File,
+        index: &mut Vec<IndexEntry>,
+        offset: &mut u64,
+        block_buf: &[u8],
+        first_key: &Option<InternalKey>,
+    ) -> Result<()> {
+        file.write_all(block_buf)?;
+        index.push(IndexEntry {
+            first_key: first_key.clone().expect("block with data must have a first key"),
+            offset: *offset,
+            len: block_buf.len() as u32,
+        });
+        *offset += block_buf.len() as u64;
+        Ok(())
+    }
+
+    fn encode_entry(key: &InternalKey, value: &StoredValue) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(key.user_key.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&key.user_key);
+        buf.extend_from_slice(&key.seq.to_le_bytes());
+        match value {
+            StoredValue::Value(v) => {
+                buf.push(VALUE_FLAG);
+                buf.extend_from_slice(&(v.len() as u32).to_le_bytes());
+                buf.extend_from_slice(v);
+            }
+            StoredValue::Tombstone => {
+                buf.push(TOMBSTONE_FLAG);
+                buf.extend_from_slice(&0u32.to_le_bytes());
+            }
+        }
+        buf
+    }
+
+    /// Look up the newest version of `key` with a sequence `<= max_seq`,
+    /// reading as few data blocks as possible. A key with several
+    /// versions can, in principle, straddle a block boundary, so this
+    /// keeps scanning into later blocks until the user key itself moves on.
+    pub fn get_at(&self, key: &[u8], max_seq: u64) -> Result<Option<StoredValue>> {
+        if self.index.is_empty() {
+            return Ok(None);
+        }
+        if key < self.min_key.as_slice() || key > self.max_key.as_slice() {
+            return Ok(None);
+        }
+        if !self.bloom.may_contain(key) {
+            return Ok(None);
+        }
+
+        let query = InternalKey::new(key.to_vec(), max_seq);
+        let first_block = self.index.partition_point(|e| e.first_key <= query).saturating_sub(1);
+
+        let mut file = File::open(&self.path)?;
+        for block_entry in &self.index[first_block..] {
+            if block_entry.first_key.user_key.as_slice() > key {
+                break;
+            }
+
+            file.seek(SeekFrom::Start(block_entry.offset))?;
+            let mut block = vec![0u8; block_entry.len as usize];
+            file.read_exact(&mut block)?;
+
+            let mut offset = 0;
+            while offset < block.len() {
+                let (ikey, value) = Self::decode_entry_at(&block, &mut offset);
+                if ikey.user_key.as_slice() > key {
+                    return Ok(None);
+                }
+                if ikey.user_key.as_slice() == key && ikey.seq <= max_seq {
+                    return Ok(Some(value));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decode one `[key_len][key][seq][flag][value_len][value]` record
+    /// starting at `data[*offset]`, advancing `offset` past it.
+    fn decode_entry_at(data: &[u8], offset: &mut usize) -> (InternalKey, StoredValue) {
+        let key_len = u32::from_le_bytes(data[*offset..*offset + 4].try_into().unwrap()) as usize;
+        *offset += 4;
+        let user_key = data[*offset..*offset + key_len].to_vec();
+        *offset += key_len;
+        let seq = u64::from_le_bytes(data[*offset..*offset + 8].try_into().unwrap());
+        *offset += 8;
+        let flag = data[*offset];
+        *offset += 1;
+        let value_len = u32::from_le_bytes(data[*offset..*offset + 4].try_into().unwrap()) as usize;
+        *offset += 4;
+        let value = data[*offset..*offset + value_len].to_vec();
+        *offset += value_len;
+
+        let stored = if flag == TOMBSTONE_FLAG {
+            StoredValue::Tombstone
+        } else {
+            StoredValue::Value(value)
+        };
+        (InternalKey::new(user_key, seq), stored)
+    }
+
+    /// Read the newest version (at or below `max_seq`) of every key with
+    /// `start <= key < end`, in ascending key order. Uses the sparse
+    /// index to skip straight to the first block that could contain
+    /// `start`, then scans forward block by block until a key `>= end`
+    /// is seen, keeping only the first (newest qualifying) version of
+    /// each key and skipping the rest of that key's run.
+    pub fn scan_at(&self, start: &[u8], end: &[u8], max_seq: u64) -> Result<Vec<(Vec<u8>, StoredValue)>> {
+        if self.index.is_empty() || start >= end {
+            return Ok(Vec::new());
+        }
+        if end <= self.min_key.as_slice() || start > self.max_key.as_slice() {
+            return Ok(Vec::new());
+        }
+
+        let query = InternalKey::new(start.to_vec(), u64::MAX);
+        let first_block = self.index.partition_point(|e| e.first_key <= query).saturating_sub(1);
+
+        let mut file = File::open(&self.path)?;
+        let mut entries: Vec<(Vec<u8>, StoredValue)> = Vec::new();
+        'blocks: for block_entry in &self.index[first_block..] {
+            if block_entry.first_key.user_key.as_slice() >= end {
+                break;
+            }
+
+            file.seek(SeekFrom::Start(block_entry.offset))?;
+            let mut block = vec![0u8; block_entry.len as usize];
+            file.read_exact(&mut block)?;
+
+            let mut offset = 0;
+            while offset < block.len() {
+                let (ikey, value) = Self::decode_entry_at(&block, &mut offset);
+                if ikey.user_key.as_slice() >= end {
+                    break 'blocks;
+                }
+                if ikey.user_key.as_slice() < start || ikey.seq > max_seq {
+                    continue;
+                }
+                match entries.last() {
+                    Some((last_key, _)) if *last_key == ikey.user_key => {}
+                    _ => entries.push((ikey.user_key, value)),
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Read every version of every key in the table, in on-disk
+    /// (ascending internal key) order. Used by compaction, which merges
+    /// whole tables rather than looking up a single key.
+    pub fn read_all(&self) -> Result<Vec<(InternalKey, StoredValue)>> {
+        let mut file = File::open(&self.path)?;
+        let mut data = vec![0u8; self.data_len as usize];
+        file.read_exact(&mut data)?;
+
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            entries.push(Self::decode_entry_at(&data, &mut offset));
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, seq: u64, value: Option<&str>) -> (InternalKey, StoredValue) {
+        let stored = match value {
+            Some(v) => StoredValue::Value(v.as_bytes().to_vec()),
+            None => StoredValue::Tombstone,
+        };
+        (InternalKey::new(key.as_bytes().to_vec(), seq), stored)
+    }
+
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("toydb_sstable_test_{name}.sst"))
+    }
+
+    /// `write` requires entries pre-sorted ascending by internal key (user
+    /// key, then seq descending) — exactly what `MemTable::sorted_entries`
+    /// already hands it in production, so tests sort the same way here.
+    fn write_sorted<P: AsRef<Path>>(
+        path: P,
+        level: u8,
+        table_id: u64,
+        mut entries: Vec<(InternalKey, StoredValue)>,
+    ) -> Result<SSTable> {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        SSTable::write(path, level, table_id, &entries)
+    }
+
+    #[test]
+    fn test_write_then_get_at_roundtrip() -> Result<()> {
+        let path = scratch_path("roundtrip");
+        let entries = vec![
+            entry("a", 1, Some("a1")),
+            entry("b", 2, Some("b1")),
+            entry("c", 3, None),
+        ];
+        let table = write_sorted(&path, 0, 7, entries)?;
+
+        assert!(matches!(table.get_at(b"a", 10)?, Some(StoredValue::Value(v)) if v == b"a1"));
+        assert!(matches!(table.get_at(b"b", 10)?, Some(StoredValue::Value(v)) if v == b"b1"));
+        assert!(matches!(table.get_at(b"c", 10)?, Some(StoredValue::Tombstone)));
+        assert!(table.get_at(b"missing", 10)?.is_none());
+
+        std::fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_at_only_sees_versions_up_to_max_seq() -> Result<()> {
+        let path = scratch_path("versions");
+        let entries = vec![entry("k", 1, Some("v1")), entry("k", 5, Some("v5"))];
+        let table = write_sorted(&path, 0, 1, entries)?;
+
+        assert!(matches!(table.get_at(b"k", 1)?, Some(StoredValue::Value(v)) if v == b"v1"));
+        assert!(matches!(table.get_at(b"k", 4)?, Some(StoredValue::Value(v)) if v == b"v1"));
+        assert!(matches!(table.get_at(b"k", 5)?, Some(StoredValue::Value(v)) if v == b"v5"));
+        assert!(table.get_at(b"k", 0)?.is_none());
+
+        std::fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_at_returns_newest_qualifying_version_per_key_in_range() -> Result<()> {
+        let path = scratch_path("scan");
+        let entries = vec![
+            entry("a", 1, Some("a1")),
+            entry("b", 1, Some("b1")),
+            entry("b", 2, Some("b2")),
+            entry("c", 1, Some("c1")),
+        ];
+        let table = write_sorted(&path, 0, 2, entries)?;
+
+        let scanned: Vec<(Vec<u8>, Vec<u8>)> = table
+            .scan_at(b"a", b"c", 10)?
+            .into_iter()
+            .map(|(k, v)| match v {
+                StoredValue::Value(v) => (k, v),
+                StoredValue::Tombstone => panic!("unexpected tombstone for {k:?}"),
+            })
+            .collect();
+        assert_eq!(
+            scanned,
+            vec![(b"a".to_vec(), b"a1".to_vec()), (b"b".to_vec(), b"b2".to_vec())]
+        );
+
+        std::fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_reopens_a_table_written_earlier() -> Result<()> {
+        let path = scratch_path("open_roundtrip");
+        let entries = vec![entry("a", 1, Some("a1")), entry("z", 9, None)];
+        let written = write_sorted(&path, 2, 42, entries)?;
+
+        let reopened = SSTable::open(&path)?;
+        assert_eq!(reopened.level, written.level);
+        assert_eq!(reopened.table_id, written.table_id);
+        assert_eq!(reopened.max_seq, written.max_seq);
+        assert_eq!(reopened.min_key, written.min_key);
+        assert_eq!(reopened.max_key, written.max_key);
+        assert!(matches!(reopened.get_at(b"a", 10)?, Some(StoredValue::Value(v)) if v == b"a1"));
+        assert!(matches!(reopened.get_at(b"z", 10)?, Some(StoredValue::Tombstone)));
+
+        std::fs::remove_file(&path).ok();
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod btree;
+pub mod lsm;
@@ -1,5 +1,7 @@
-pub mod btree;
-pub mod lsm;
+pub mod engines;
+pub mod wal;
+
+use wal::WriteBatch;
 
 /// Common trait for key-value storage engine
 pub trait KvEngine {
@@ -10,8 +12,19 @@ pub trait KvEngine {
     fn put(&mut self, key: &[u8], value: &[u8]);
 
     /// Get a value by key
-    fn get(&self, key: &[u8]) -> Option<&Vec<u8>>;
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
 
     /// Delete a key-value pair
     fn delete(&mut self, key: &[u8]);
+
+    /// Apply every operation in `batch` as a single atomic unit: either
+    /// all of them become durable and visible, or (if a crash interrupts
+    /// the write) none do.
+    fn write(&mut self, batch: WriteBatch);
+
+    /// Iterate over every live entry with `start <= key < end`, in
+    /// ascending key order. Where the same key exists in more than one
+    /// underlying source, only the most recently written version is
+    /// yielded.
+    fn scan<'a>(&'a self, start: &[u8], end: &[u8]) -> Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
 }
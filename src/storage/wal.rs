@@ -1,5 +1,6 @@
-use std::{fs::{File, OpenOptions}, io::{BufReader, BufWriter, Read, Write, Result}, path::{Path, PathBuf}};
+use std::{fs::{File, OpenOptions}, io::{BufWriter, Write, Result}, path::{Path, PathBuf}};
 
+use crc32fast::Hasher;
 
 /*
 wal is a sequential append only log
@@ -7,14 +8,19 @@ write immediately on every write (put/delete)
 cleanup/compact after memtable flushes
 wal is temporary
 sequential writes, sequential reads during recovery
-*/
 
-// Considerations for WAL Design: https://x.com/jorandirkgreef/status/1892109953608958252struct Wal {
-    // length: u8,
-    // index: u8,
-    // data: u32,
-    // // checksum: u?
+The file is divided into fixed-size 32 KB blocks. Within a block, records
+are framed as [checksum: 4][length: 2][type: 1][payload], where `type` is
+one of FULL/FIRST/MIDDLE/LAST. A logical `WalEntry` that fits in the
+remaining block space is written as a single FULL record; one that
+doesn't is split across consecutive blocks as FIRST + zero-or-more
+MIDDLE + LAST fragments. This bounds the damage a single corrupt byte
+can do to one block, removes any cap on entry size, and keeps sequential
+scans aligned to block boundaries — the same scheme LevelDB's log format
+uses.
+*/
 
+// Considerations for WAL Design: https://x.com/jorandirkgreef/status/1892109953608958252
 
 /// Tag indicating a Put entry in the Write-Ahead Log.
 const PUT_TAG: u8 = 0;
@@ -22,14 +28,30 @@ const PUT_TAG: u8 = 0;
 /// Tag indicating a Delete entry in the Write-Ahead Log.
 const DELETE_TAG: u8 = 1;
 
+/// Tag indicating a WriteBatch: a sequence of ops framed as one logical
+/// record, so they're replayed as a single all-or-nothing unit.
+const BATCH_TAG: u8 = 2;
+
 /// Size of the operation tag byte (1 byte for u8)
 const TAG_SIZE: usize = 1;
 
-/// Size of length prefix fields (4 bytes for u32)
+/// Size of length prefix fields used within an encoded entry (4 bytes for u32)
 const LENGTH_FIELD_SIZE: usize = 4;
 
-/// Maximum size of a single WAL entry (1MB)
-const MAX_ENTRY_SIZE: usize = 1 * 1024 * 1024;
+/// Size of a fixed block in the block-structured log.
+const BLOCK_SIZE: usize = 32 * 1024;
+
+/// Size of a physical record header: checksum(4) + length(2) + type(1).
+const RECORD_HEADER_SIZE: usize = 4 + 2 + 1;
+
+/// Physical record type: the whole logical record fit in one block.
+const RECORD_FULL: u8 = 1;
+/// Physical record type: first fragment of a logical record spanning blocks.
+const RECORD_FIRST: u8 = 2;
+/// Physical record type: a middle fragment of a logical record.
+const RECORD_MIDDLE: u8 = 3;
+/// Physical record type: the last fragment of a logical record.
+const RECORD_LAST: u8 = 4;
 
 
 /// Represents an entry in the Write-Ahead Log.
@@ -39,127 +61,71 @@ pub enum WalEntry {
     Delete { key: Vec<u8> },
 }
 
-/// Write-Ahead Log for durable storage of operations.
-pub struct Wal {
-    path: PathBuf,
-    writer: BufWriter<File>
-}
-
-impl Wal {
-    /// Opens or creates a WAL file at the given path.
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        let file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)?;
-        let writer = BufWriter::new(file);
-        Ok(Self { path, writer })
-    }
-
-    /// Calculate the encoded size of a WAL entry (without length prefix)
-    fn encoded_size(entry: &WalEntry) -> usize {
-        match entry {
+impl WalEntry {
+    /// Encode this entry's logical body (tag + key/value), independent of
+    /// how it ends up fragmented across physical blocks.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        match self {
             WalEntry::Put { key, value } => {
-                TAG_SIZE +
-                LENGTH_FIELD_SIZE + // key length field
-                key.len() +
-                LENGTH_FIELD_SIZE + // value length field
-                value.len()
+                let mut buf = Vec::with_capacity(
+                    TAG_SIZE + LENGTH_FIELD_SIZE + key.len() + LENGTH_FIELD_SIZE + value.len(),
+                );
+                buf.push(PUT_TAG);
+                buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                buf.extend_from_slice(key);
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buf.extend_from_slice(value);
+                buf
             }
             WalEntry::Delete { key } => {
-                TAG_SIZE +
-                LENGTH_FIELD_SIZE + // key length field
-                key.len()
+                let mut buf = Vec::with_capacity(TAG_SIZE + LENGTH_FIELD_SIZE + key.len());
+                buf.push(DELETE_TAG);
+                buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                buf.extend_from_slice(key);
+                buf
             }
         }
     }
 
-    /// Write an encoded entry to the writer (without length prefix)
-    fn write_entry(&mut self, entry: &WalEntry) -> Result<()> {
-        match entry {
-            WalEntry::Put { key, value } => {
-                self.writer.write_all(&[PUT_TAG])?;
-                self.writer.write_all(&(key.len() as u32).to_le_bytes())?;
-                self.writer.write_all(key)?;
-                self.writer.write_all(&(value.len() as u32).to_le_bytes())?;
-                self.writer.write_all(value)?;
-            }
-            WalEntry::Delete { key } => {
-                self.writer.write_all(&[DELETE_TAG])?;
-                self.writer.write_all(&(key.len() as u32).to_le_bytes())?;
-                self.writer.write_all(key)?;
-            }
-        }
-        Ok(())
-    }
-
-    /// Appends an entry to the WAL and flushes it to disk for durability.
-    pub fn append(&mut self, entry: &WalEntry) -> Result<()> {
-        // Calculate entry size and validate
-        let entry_size = Self::encoded_size(entry);
-
-        if entry_size > MAX_ENTRY_SIZE {
+    /// Decode a logical body back into an entry, requiring the whole
+    /// buffer to be consumed by exactly one op.
+    /// Format: [tag: 1 byte][key_len: 4 bytes][key][value_len: 4 bytes][value] for Put
+    ///         [tag: 1 byte][key_len: 4 bytes][key] for Delete
+    fn decode(buffer: &[u8]) -> Result<WalEntry> {
+        let (entry, consumed) = Self::decode_at(buffer, 0)?;
+        if consumed != buffer.len() {
             return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                format!("Entry size {} exceeds maximum {}", entry_size, MAX_ENTRY_SIZE),
+                std::io::ErrorKind::InvalidData,
+                "Entry buffer has trailing bytes after a single op",
             ));
         }
-
-        // Write length prefix first (format: [length: u32][entry_data...])
-        self.writer.write_all(&(entry_size as u32).to_le_bytes())?;
-        
-        // Write entry data
-        self.write_entry(entry)?;
-        
-        // Flush for durability
-        self.writer.flush()?;
-        
-        Ok(())
-    }
-
-    /// Convenience method to append a Put entry
-    pub fn append_put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
-        self.append(&WalEntry::Put {
-            key: key.to_vec(),
-            value: value.to_vec(),
-        })
-    }
-
-    /// Convenience method to append a Delete entry
-    pub fn append_delete(&mut self, key: &[u8]) -> Result<()> {
-        self.append(&WalEntry::Delete {
-            key: key.to_vec(),
-        })
+        Ok(entry)
     }
 
-    /// Parse a single entry from a buffer
-    /// Format: [tag: 1 byte][key_len: 4 bytes][key][value_len: 4 bytes][value] for Put
-    ///         [tag: 1 byte][key_len: 4 bytes][key] for Delete
-    fn parse_entry(buffer: &[u8]) -> Result<WalEntry> {
-        if buffer.is_empty() {
+    /// Decode one op starting at `buffer[offset]`, returning it along
+    /// with the offset just past its encoded bytes. Used both for a
+    /// standalone entry and for each op packed into a WriteBatch.
+    fn decode_at(buffer: &[u8], offset: usize) -> Result<(WalEntry, usize)> {
+        if offset >= buffer.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Entry buffer is empty",
             ));
         }
-        let tag = buffer[0];
-        let mut offset = TAG_SIZE;
-        
-        // Read key length
+        let tag = buffer[offset];
+        let mut offset = offset + TAG_SIZE;
+
         if offset + LENGTH_FIELD_SIZE > buffer.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
                 "Entry buffer too short for key length",
             ));
         }
-        
         let key_len = u32::from_le_bytes(
             buffer[offset..offset + LENGTH_FIELD_SIZE].try_into().unwrap()
         ) as usize;
         offset += LENGTH_FIELD_SIZE;
-        
-        // Read key
+
         if offset + key_len > buffer.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidData,
@@ -168,10 +134,9 @@ impl Wal {
         }
         let key = buffer[offset..offset + key_len].to_vec();
         offset += key_len;
-        
+
         match tag {
             PUT_TAG => {
-                // Read value length
                 if offset + LENGTH_FIELD_SIZE > buffer.len() {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
@@ -182,8 +147,7 @@ impl Wal {
                     buffer[offset..offset + LENGTH_FIELD_SIZE].try_into().unwrap()
                 ) as usize;
                 offset += LENGTH_FIELD_SIZE;
-                
-                // Read value
+
                 if offset + value_len > buffer.len() {
                     return Err(std::io::Error::new(
                         std::io::ErrorKind::InvalidData,
@@ -191,60 +155,448 @@ impl Wal {
                     ));
                 }
                 let value = buffer[offset..offset + value_len].to_vec();
-                
-                Ok(WalEntry::Put { key, value })
+                offset += value_len;
+                Ok((WalEntry::Put { key, value }, offset))
             }
-            DELETE_TAG => {
-                Ok(WalEntry::Delete { key })
+            DELETE_TAG => Ok((WalEntry::Delete { key }, offset)),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown tag: {}", tag),
+            )),
+        }
+    }
+}
+
+/// One record replayed from the WAL: either a single operation, or a
+/// batch of operations (from a `WriteBatch`) that were written as a
+/// single logical record and must be applied together.
+#[derive(Debug, Clone)]
+pub enum WalRecord {
+    Entry(WalEntry),
+    Batch(Vec<WalEntry>),
+}
+
+impl WalRecord {
+    /// Encode a batch of ops as `[BATCH_TAG][num_ops: 4][op...]`.
+    fn encode_batch(ops: &[WalEntry]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.push(BATCH_TAG);
+        buf.extend_from_slice(&(ops.len() as u32).to_le_bytes());
+        for op in ops {
+            buf.extend_from_slice(&op.encode());
+        }
+        buf
+    }
+
+    /// Decode a logical record's body, dispatching on its leading tag.
+    fn decode(data: &[u8]) -> Result<WalRecord> {
+        if data.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Record buffer is empty",
+            ));
+        }
+
+        match data[0] {
+            PUT_TAG | DELETE_TAG => Ok(WalRecord::Entry(WalEntry::decode(data)?)),
+            BATCH_TAG => {
+                let mut offset = TAG_SIZE;
+                if offset + LENGTH_FIELD_SIZE > data.len() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Batch buffer too short for op count",
+                    ));
+                }
+                let num_ops = u32::from_le_bytes(
+                    data[offset..offset + LENGTH_FIELD_SIZE].try_into().unwrap()
+                ) as usize;
+                offset += LENGTH_FIELD_SIZE;
+
+                let mut ops = Vec::with_capacity(num_ops);
+                for _ in 0..num_ops {
+                    let (op, next_offset) = WalEntry::decode_at(data, offset)?;
+                    ops.push(op);
+                    offset = next_offset;
+                }
+                if offset != data.len() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Batch buffer has trailing bytes after its declared ops",
+                    ));
+                }
+                Ok(WalRecord::Batch(ops))
             }
-            _ => {
-                Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Unknown tag: {}", tag),
-                ))
+            tag => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Unknown tag: {}", tag),
+            )),
+        }
+    }
+}
+
+/// A write batch error: a batch was built with a bounded capacity and
+/// exceeded it, so callers must flush what they have and start a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBatchFull;
+
+impl std::fmt::Display for WriteBatchFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WriteBatch exceeded its configured capacity")
+    }
+}
+
+impl std::error::Error for WriteBatchFull {}
+
+/// An ordered list of put/delete operations applied as a single atomic
+/// unit via `KvEngine::write`: either every op becomes durable and
+/// visible, or — if a crash interrupts the WAL write — none of them do.
+#[derive(Debug, Clone, Default)]
+pub struct WriteBatch {
+    ops: Vec<WalEntry>,
+    size_bytes: usize,
+    /// (max_ops, max_bytes), if this batch was built with a bounded capacity.
+    limit: Option<(usize, usize)>,
+}
+
+impl WriteBatch {
+    /// An unbounded batch; ops can be added until memory runs out.
+    pub fn new() -> Self {
+        Self {
+            ops: Vec::new(),
+            size_bytes: 0,
+            limit: None,
+        }
+    }
+
+    /// A batch that rejects further ops once it would exceed `max_ops`
+    /// operations or `max_bytes` of encoded size, so callers can flush in
+    /// bounded-size chunks instead of growing one batch without limit.
+    pub fn with_capacity(max_ops: usize, max_bytes: usize) -> Self {
+        Self {
+            ops: Vec::new(),
+            size_bytes: 0,
+            limit: Some((max_ops, max_bytes)),
+        }
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) -> std::result::Result<(), WriteBatchFull> {
+        self.push(WalEntry::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        })
+    }
+
+    pub fn delete(&mut self, key: &[u8]) -> std::result::Result<(), WriteBatchFull> {
+        self.push(WalEntry::Delete { key: key.to_vec() })
+    }
+
+    fn push(&mut self, entry: WalEntry) -> std::result::Result<(), WriteBatchFull> {
+        let entry_size = entry.encode().len();
+        if let Some((max_ops, max_bytes)) = self.limit {
+            if self.ops.len() + 1 > max_ops || self.size_bytes + entry_size > max_bytes {
+                return Err(WriteBatchFull);
             }
         }
+        self.size_bytes += entry_size;
+        self.ops.push(entry);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    pub fn ops(&self) -> &[WalEntry] {
+        &self.ops
     }
+}
+
+/// Write-Ahead Log for durable storage of operations, stored as a
+/// block-structured log (see module docs).
+pub struct Wal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Byte offset within the current 32 KB block.
+    block_offset: usize,
+}
+
+impl Wal {
+    /// Opens or creates a WAL file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let existing_len = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let writer = BufWriter::new(file);
+        Ok(Self {
+            path,
+            writer,
+            block_offset: (existing_len as usize) % BLOCK_SIZE,
+        })
+    }
+
+    /// The path this WAL is writing to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn checksum(record_type: u8, payload: &[u8]) -> u32 {
+        let mut hasher = Hasher::new();
+        hasher.update(&[record_type]);
+        hasher.update(payload);
+        hasher.finalize()
+    }
+
+    /// Write one physical record (header + payload), padding to the next
+    /// block boundary first if too little space remains for a header.
+    fn write_physical_record(&mut self, record_type: u8, payload: &[u8]) -> Result<()> {
+        let leftover = BLOCK_SIZE - self.block_offset;
+        if leftover < RECORD_HEADER_SIZE {
+            // Not enough room left in this block for even a header; pad
+            // with zeros and move on to the next block.
+            self.writer.write_all(&vec![0u8; leftover])?;
+            self.block_offset = 0;
+        }
+
+        let crc = Self::checksum(record_type, payload);
+        self.writer.write_all(&crc.to_le_bytes())?;
+        self.writer.write_all(&(payload.len() as u16).to_le_bytes())?;
+        self.writer.write_all(&[record_type])?;
+        self.writer.write_all(payload)?;
+
+        self.block_offset += RECORD_HEADER_SIZE + payload.len();
+        Ok(())
+    }
+
+    /// Fragment a logical record across as many physical records as needed.
+    fn write_logical_record(&mut self, data: &[u8]) -> Result<()> {
+        let mut remaining = data;
+        let mut begin = true;
 
-    /// Replay all entries from a WAL file
-    /// Opens the file for reading and parses all entries sequentially
-    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Vec<WalEntry>> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let mut entries = Vec::new();
-        
         loop {
-            // Read the length prefix
-            let mut length_bytes = [0u8; LENGTH_FIELD_SIZE];
-            match reader.read_exact(&mut length_bytes) {
-                Ok(()) => {}
-                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                    break;
+            let leftover = BLOCK_SIZE - self.block_offset;
+            let avail = if leftover < RECORD_HEADER_SIZE {
+                BLOCK_SIZE - RECORD_HEADER_SIZE
+            } else {
+                leftover - RECORD_HEADER_SIZE
+            };
+
+            let fragment_len = remaining.len().min(avail);
+            let is_last_fragment = fragment_len == remaining.len();
+
+            let record_type = match (begin, is_last_fragment) {
+                (true, true) => RECORD_FULL,
+                (true, false) => RECORD_FIRST,
+                (false, true) => RECORD_LAST,
+                (false, false) => RECORD_MIDDLE,
+            };
+
+            self.write_physical_record(record_type, &remaining[..fragment_len])?;
+            remaining = &remaining[fragment_len..];
+            begin = false;
+
+            if remaining.is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends an entry to the WAL and flushes it to disk for durability.
+    pub fn append(&mut self, entry: &WalEntry) -> Result<()> {
+        self.append_bytes(&entry.encode())
+    }
+
+    /// Appends a `WriteBatch` as a single logical record, so it replays
+    /// as one atomic unit: either every op in it is recovered, or (if a
+    /// crash tore the write) none are.
+    pub fn append_batch(&mut self, batch: &WriteBatch) -> Result<()> {
+        self.append_bytes(&WalRecord::encode_batch(batch.ops()))
+    }
+
+    fn append_bytes(&mut self, body: &[u8]) -> Result<()> {
+        self.write_logical_record(body)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Convenience method to append a Put entry
+    pub fn append_put(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.append(&WalEntry::Put {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        })
+    }
+
+    /// Convenience method to append a Delete entry
+    pub fn append_delete(&mut self, key: &[u8]) -> Result<()> {
+        self.append(&WalEntry::Delete {
+            key: key.to_vec(),
+        })
+    }
+
+    /// Truncate the WAL back to empty. Called right after a MemTable
+    /// flush has made everything the WAL was holding durable in an
+    /// SSTable — from that point on, recovery only needs whatever gets
+    /// appended after this call, so there's no reason to keep replaying
+    /// (and growing) the same entries forever.
+    pub fn truncate(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().set_len(0)?;
+        self.block_offset = 0;
+        Ok(())
+    }
+
+    /// Try to parse one physical record at `data[offset..]`: a full
+    /// header within the block containing `offset`, a recognized type, a
+    /// payload that doesn't run past the file or across a block boundary,
+    /// and a matching checksum. `None` means there's no valid record
+    /// starting at this exact byte — either a torn write (if this is the
+    /// tail of the file) or a stray byte of corruption.
+    fn try_parse_record(data: &[u8], offset: usize) -> Option<(u8, &[u8], usize)> {
+        let block_offset = offset % BLOCK_SIZE;
+        let block_end = offset - block_offset + BLOCK_SIZE;
+
+        if offset + RECORD_HEADER_SIZE > data.len() {
+            return None;
+        }
+
+        let crc = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let length = u16::from_le_bytes(data[offset + 4..offset + 6].try_into().unwrap()) as usize;
+        let record_type = data[offset + 6];
+        if !(RECORD_FULL..=RECORD_LAST).contains(&record_type) {
+            return None;
+        }
+
+        let payload_start = offset + RECORD_HEADER_SIZE;
+        let payload_end = payload_start + length;
+        if payload_end > data.len() || payload_end > block_end {
+            return None;
+        }
+
+        let payload = &data[payload_start..payload_end];
+        if Self::checksum(record_type, payload) != crc {
+            return None;
+        }
+
+        Some((record_type, payload, payload_end))
+    }
+
+    /// Replay all entries from a WAL file.
+    ///
+    /// Reassembles fragmented logical records block by block, validating
+    /// each physical record's checksum. Whenever a byte offset doesn't
+    /// hold a valid record — a torn write, a stray fragment abandoned by
+    /// a crash, or a handful of garbage bytes left in front of whatever
+    /// was written after a restart — any fragment in progress is dropped
+    /// and replay resyncs by scanning forward one byte at a time (a
+    /// record never crosses a block boundary, so the scan never needs to
+    /// go past the start of the next block before a genuinely valid,
+    /// independently-checksummed record can resume). Everything
+    /// successfully recovered this way is still returned; nothing after
+    /// the last fully-recovered record is, since there's no way to tell a
+    /// genuinely incomplete tail apart from unrelated corruption.
+    ///
+    /// The file is then truncated back to just past that last recovered
+    /// record, so a future append starts from a clean boundary instead of
+    /// building on top of whatever corrupt or incomplete bytes used to
+    /// follow it.
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Vec<WalRecord>> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let mut records = Vec::new();
+        let mut offset = 0usize;
+        let mut fragment: Option<Vec<u8>> = None;
+        let mut valid_offset = 0usize;
+
+        while offset < data.len() {
+            let block_offset = offset % BLOCK_SIZE;
+            let leftover_in_block = BLOCK_SIZE - block_offset;
+
+            if leftover_in_block < RECORD_HEADER_SIZE {
+                // Padding to the end of the block; skip to the next one.
+                offset += leftover_in_block.min(data.len() - offset);
+                continue;
+            }
+
+            let Some((record_type, payload, payload_end)) = Self::try_parse_record(&data, offset) else {
+                // No valid record starts here. Whatever fragment was in
+                // progress can never be completed now, and the stray
+                // byte(s) here might just be the start of a torn write —
+                // resync by trying the next byte.
+                fragment = None;
+                offset += 1;
+                continue;
+            };
+
+            match record_type {
+                RECORD_FULL => {
+                    // Any in-progress fragment is now known stale: a FULL
+                    // record can only start once whatever held it open
+                    // before (a torn pre-crash FIRST/MIDDLE run) has been
+                    // abandoned. Drop it and read this record normally.
+                    fragment = None;
+                    match WalRecord::decode(payload) {
+                        Ok(record) => {
+                            records.push(record);
+                            valid_offset = payload_end;
+                        }
+                        Err(_) => {
+                            offset += 1;
+                            continue;
+                        }
+                    }
                 }
-                Err(e) => {
-                    return Err(e);
+                RECORD_FIRST => {
+                    // Same reasoning as RECORD_FULL above: discard any
+                    // stale fragment rather than aborting the whole replay.
+                    fragment = Some(payload.to_vec());
                 }
+                RECORD_MIDDLE => match fragment.as_mut() {
+                    Some(buf) => buf.extend_from_slice(payload),
+                    None => {
+                        offset += 1;
+                        continue;
+                    }
+                },
+                RECORD_LAST => match fragment.take() {
+                    Some(mut buf) => {
+                        buf.extend_from_slice(payload);
+                        match WalRecord::decode(&buf) {
+                            Ok(record) => {
+                                records.push(record);
+                                valid_offset = payload_end;
+                            }
+                            Err(_) => {
+                                offset += 1;
+                                continue;
+                            }
+                        }
+                    }
+                    None => {
+                        offset += 1;
+                        continue;
+                    }
+                },
+                _ => unreachable!(),
             }
-            
-            let entry_length = u32::from_le_bytes(length_bytes) as usize;
-            
-            if entry_length > MAX_ENTRY_SIZE {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    format!("Entry length {} exceeds maximum {}", entry_length, MAX_ENTRY_SIZE),
-                ));
-            }
-            
-            // Read and parse the entry
-            let mut entry_buffer = vec![0u8; entry_length];
-            reader.read_exact(&mut entry_buffer)?;
-            let entry = Self::parse_entry(&entry_buffer)?;
-            entries.push(entry);
+
+            offset = payload_end;
         }
-        
-        Ok(entries)
-    }
 
+        if data.len() > valid_offset {
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(valid_offset as u64)?;
+        }
+
+        Ok(records)
+    }
 }
 
 
@@ -266,7 +618,7 @@ mod tests {
     #[test]
     fn test_wal() -> Result<()> {
         let path = "example.wal";
-        
+
         // Clean up any existing file from previous test runs
         std::fs::remove_file(path).ok();
 
@@ -281,36 +633,284 @@ mod tests {
 
         // Replay them
         {
-            let entries = Wal::replay(path)?;
-            assert_eq!(entries.len(), 3);
-            
-            // Verify entries
-            match &entries[0] {
-                WalEntry::Put { key, value } => {
+            let records = Wal::replay(path)?;
+            assert_eq!(records.len(), 3);
+
+            match &records[0] {
+                WalRecord::Entry(WalEntry::Put { key, value }) => {
                     assert_eq!(key, b"key1");
                     assert_eq!(value, b"value1");
                 }
                 _ => panic!("Expected Put entry"),
             }
-            
-            match &entries[1] {
-                WalEntry::Put { key, value } => {
+
+            match &records[1] {
+                WalRecord::Entry(WalEntry::Put { key, value }) => {
                     assert_eq!(key, b"key2");
                     assert_eq!(value, b"value2");
                 }
                 _ => panic!("Expected Put entry"),
             }
-            
-            match &entries[2] {
-                WalEntry::Delete { key } => {
+
+            match &records[2] {
+                WalRecord::Entry(WalEntry::Delete { key }) => {
                     assert_eq!(key, b"key1");
                 }
                 _ => panic!("Expected Delete entry"),
             }
         }
 
-        // Cleanup
         std::fs::remove_file(path).ok();
         Ok(())
     }
+
+    #[test]
+    fn test_wal_entry_larger_than_one_block_is_fragmented_and_replayed() -> Result<()> {
+        let path = "example_large.wal";
+        std::fs::remove_file(path).ok();
+
+        // Bigger than a single 32 KB block, so this must span FIRST/MIDDLE/LAST fragments.
+        let big_value = vec![0xAB; BLOCK_SIZE * 3 + 123];
+
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"big", &big_value)?;
+            wal.append_put(b"small", b"value")?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 2);
+        match &records[0] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"big");
+                assert_eq!(value, &big_value);
+            }
+            _ => panic!("Expected Put entry"),
+        }
+        match &records[1] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"small");
+                assert_eq!(value, b"value");
+            }
+            _ => panic!("Expected Put entry"),
+        }
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_wal_incomplete_fragment_run_is_dropped() -> Result<()> {
+        let path = "example_incomplete_fragment.wal";
+        std::fs::remove_file(path).ok();
+
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"key1", b"value1")?;
+        }
+        let good_len = std::fs::metadata(path)?.len();
+
+        // Append a FIRST fragment with no following LAST, simulating a
+        // crash partway through writing a large entry.
+        {
+            let file = OpenOptions::new().append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            let payload = vec![0u8; 64];
+            let crc = Wal::checksum(RECORD_FIRST, &payload);
+            writer.write_all(&crc.to_le_bytes())?;
+            writer.write_all(&(payload.len() as u16).to_le_bytes())?;
+            writer.write_all(&[RECORD_FIRST])?;
+            writer.write_all(&payload)?;
+            writer.flush()?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 1);
+        // The dangling fragment should have been truncated away, so a
+        // subsequent append starts from a clean boundary.
+        assert_eq!(std::fs::metadata(path)?.len(), good_len);
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_truncates_a_torn_trailing_write() -> Result<()> {
+        let path = "example_torn_tail.wal";
+        std::fs::remove_file(path).ok();
+
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"key1", b"value1")?;
+        }
+        let good_len = std::fs::metadata(path)?.len();
+
+        // Simulate a crash mid-write of a second record: only part of its
+        // header made it to disk.
+        {
+            let file = OpenOptions::new().append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            writer.write_all(&[0x01, 0x02, 0x03])?;
+            writer.flush()?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(std::fs::metadata(path)?.len(), good_len);
+
+        // Replaying again (e.g. a second restart before any new writes)
+        // is stable: nothing left to truncate, same result either way.
+        let records_again = Wal::replay(path)?;
+        assert_eq!(records_again.len(), 1);
+        assert_eq!(std::fs::metadata(path)?.len(), good_len);
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_dangling_fragment_does_not_swallow_records_written_after_restart() -> Result<()> {
+        let path = "example_dangling_fragment_reopen.wal";
+        std::fs::remove_file(path).ok();
+
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"before", b"value1")?;
+        }
+
+        // Simulate a crash partway through a large entry: a FIRST fragment
+        // with no following LAST.
+        {
+            let file = OpenOptions::new().append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            let payload = vec![0u8; 64];
+            let crc = Wal::checksum(RECORD_FIRST, &payload);
+            writer.write_all(&crc.to_le_bytes())?;
+            writer.write_all(&(payload.len() as u16).to_le_bytes())?;
+            writer.write_all(&[RECORD_FIRST])?;
+            writer.write_all(&payload)?;
+            writer.flush()?;
+        }
+
+        // Process restarts, reopens the same WAL, and resumes appending.
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"after_restart", b"value2")?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 2);
+        match &records[0] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"before");
+                assert_eq!(value, b"value1");
+            }
+            _ => panic!("Expected Put entry"),
+        }
+        match &records[1] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"after_restart");
+                assert_eq!(value, b"value2");
+            }
+            _ => panic!("Expected Put entry"),
+        }
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_header_torn_write_does_not_swallow_records_written_after_restart() -> Result<()> {
+        let path = "example_sub_header_torn_write.wal";
+        std::fs::remove_file(path).ok();
+
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"before", b"value1")?;
+        }
+
+        // Simulate a crash that left only a few stray bytes behind —
+        // fewer than a full record header (7 bytes), so this is neither a
+        // recognizable record nor end-of-block padding.
+        {
+            let file = OpenOptions::new().append(true).open(path)?;
+            let mut writer = BufWriter::new(file);
+            writer.write_all(&[0xFF, 0x00, 0x12])?;
+            writer.flush()?;
+        }
+
+        // Process restarts, reopens the same WAL, and resumes appending
+        // right after the stray bytes (same block, no resync on write).
+        {
+            let mut wal = Wal::open(path)?;
+            wal.append_put(b"after_restart", b"value2")?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 2);
+        match &records[0] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"before");
+                assert_eq!(value, b"value1");
+            }
+            _ => panic!("Expected Put entry"),
+        }
+        match &records[1] {
+            WalRecord::Entry(WalEntry::Put { key, value }) => {
+                assert_eq!(key, b"after_restart");
+                assert_eq!(value, b"value2");
+            }
+            _ => panic!("Expected Put entry"),
+        }
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_batch_replays_as_one_all_or_nothing_record() -> Result<()> {
+        let path = "example_batch.wal";
+        std::fs::remove_file(path).ok();
+
+        {
+            let mut wal = Wal::open(path)?;
+            let mut batch = WriteBatch::new();
+            batch.put(b"key1", b"value1").unwrap();
+            batch.put(b"key2", b"value2").unwrap();
+            batch.delete(b"key1").unwrap();
+            wal.append_batch(&batch)?;
+        }
+
+        let records = Wal::replay(path)?;
+        assert_eq!(records.len(), 1);
+        match &records[0] {
+            WalRecord::Batch(ops) => {
+                assert_eq!(ops.len(), 3);
+                match &ops[0] {
+                    WalEntry::Put { key, value } => {
+                        assert_eq!(key, b"key1");
+                        assert_eq!(value, b"value1");
+                    }
+                    _ => panic!("Expected Put op"),
+                }
+                match &ops[2] {
+                    WalEntry::Delete { key } => assert_eq!(key, b"key1"),
+                    _ => panic!("Expected Delete op"),
+                }
+            }
+            _ => panic!("Expected Batch record"),
+        }
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_batch_with_capacity_rejects_once_full() {
+        let mut batch = WriteBatch::with_capacity(2, 1024);
+        batch.put(b"key1", b"value1").unwrap();
+        batch.put(b"key2", b"value2").unwrap();
+        assert_eq!(batch.put(b"key3", b"value3"), Err(WriteBatchFull));
+        assert_eq!(batch.len(), 2);
+    }
 }